@@ -1,12 +1,12 @@
-//! Tests for `submit_tx` RPC method.
+//! Tests for `submit_tx` and related RPC methods.
 
 // External deps
 use jsonrpc_core::types::{Failure, Output};
 use num::BigUint;
 // Workspace deps
 use models::node::{
-    tx::{PackedEthSignature, Transfer, TxSignature},
-    Address, FranklinTx, TokenId,
+    tx::{ChangePubKey, PackedEthSignature, Transfer, TxSignature},
+    Address, FranklinTx, Nonce, TokenId,
 };
 use server::api_server::rpc_server::RpcErrorCodes;
 use testkit::zksync_account::ZksyncAccount;
@@ -31,6 +31,20 @@ impl<'a> SubmitTxTester<'a> {
         TestExecutor::execute_test("Unpackable token amount", || self.unpackable_token_amount())
             .await;
         TestExecutor::execute_test("Unpackable fee amount", || self.unpackable_fee_amount()).await;
+        TestExecutor::execute_test("Batch of transactions", || self.batch()).await;
+        TestExecutor::execute_test("Batch with broken nonce order", || {
+            self.batch_incorrect_order()
+        })
+        .await;
+        TestExecutor::execute_test("Simulate too low fee", || self.simulate_low_fee()).await;
+        TestExecutor::execute_test("Simulate valid transfer", || self.simulate_valid_transfer())
+            .await;
+        TestExecutor::execute_test("Account ID inferred from state", || {
+            self.account_id_inferred_from_state()
+        })
+        .await;
+        TestExecutor::execute_test("Account not found", || self.account_not_found()).await;
+        TestExecutor::execute_test("Multisig transfer", || self.multisig_transfer()).await;
 
         Ok(())
     }
@@ -44,6 +58,28 @@ impl<'a> SubmitTxTester<'a> {
         }
     }
 
+    /// Like `check_rpc_code`, but additionally returns the `field` of the
+    /// error's `data` payload, so callers can assert on its actual value
+    /// rather than just its presence (a stub implementation could stuff a
+    /// placeholder under the right key and still pass a presence-only check).
+    fn check_rpc_code_data(
+        &self,
+        output: Failure,
+        expected_code: RpcErrorCodes,
+        field: &str,
+    ) -> serde_json::Value {
+        self.check_rpc_code(output.clone(), expected_code);
+
+        let data = output
+            .error
+            .data
+            .as_ref()
+            .unwrap_or_else(|| panic!("Expected `data` payload in error response: {:?}", output));
+        data.get(field)
+            .unwrap_or_else(|| panic!("Expected `data.{}` in error response: {:?}", field, output))
+            .clone()
+    }
+
     pub async fn no_eth_signature(&self) -> Result<(), failure::Error> {
         let main_account = &self.0.main_account;
 
@@ -98,7 +134,26 @@ impl<'a> SubmitTxTester<'a> {
                 );
             }
             Output::Failure(v) => {
-                self.check_rpc_code(v, RpcErrorCodes::IncorrectEthSignature.into());
+                // The recovered signer address must differ from the expected
+                // one, so wallets can tell the user which account actually
+                // signed instead of just "signature invalid".
+                let signer = self.check_rpc_code_data(
+                    v,
+                    RpcErrorCodes::IncorrectEthSignature.into(),
+                    "signer",
+                );
+                let recovered = signer
+                    .get("recovered")
+                    .and_then(|v| v.as_str())
+                    .expect("`signer.recovered` must be a string address");
+                let expected = signer
+                    .get("expected")
+                    .and_then(|v| v.as_str())
+                    .expect("`signer.expected` must be a string address");
+                assert_ne!(
+                    recovered, expected,
+                    "recovered signer must differ from the expected one for a bad signature"
+                );
             }
         };
 
@@ -124,7 +179,18 @@ impl<'a> SubmitTxTester<'a> {
                 panic!("Got successful response for tx with too low fee: {:?}", v);
             }
             Output::Failure(v) => {
-                self.check_rpc_code(v, RpcErrorCodes::FeeTooLow.into());
+                // The minimum acceptable fee lets the client bump the fee and
+                // resubmit without guessing.
+                let min_fee = self.check_rpc_code_data(v, RpcErrorCodes::FeeTooLow.into(), "min_fee");
+                let min_fee = min_fee
+                    .as_u64()
+                    .expect("`min_fee` must be a numeric amount");
+                assert!(
+                    min_fee > transfer_fee as u64,
+                    "min_fee ({}) must be greater than the rejected fee ({})",
+                    min_fee,
+                    transfer_fee
+                );
             }
         };
 
@@ -155,7 +221,20 @@ impl<'a> SubmitTxTester<'a> {
                 );
             }
             Output::Failure(v) => {
-                self.check_rpc_code(v, RpcErrorCodes::IncorrectTx.into());
+                // The expected account ID, resolved from state, lets the
+                // client correct and resubmit instead of guessing.
+                let expected_id = self.check_rpc_code_data(
+                    v,
+                    RpcErrorCodes::IncorrectTx.into(),
+                    "expected_account_id",
+                );
+                let expected_id = expected_id
+                    .as_u64()
+                    .expect("`expected_account_id` must be numeric");
+                assert_ne!(
+                    expected_id, incorrect_account_id as u64,
+                    "expected_account_id must not echo back the rejected id"
+                );
             }
         };
 
@@ -219,17 +298,399 @@ impl<'a> SubmitTxTester<'a> {
         Ok(())
     }
 
-    /// Creates signed transfer without any checks for correctness.
-    fn sign_transfer(
-        from: &ZksyncAccount,
+    /// Submits several dependent transfers as a single batch and expects them
+    /// to be accepted and enqueued atomically, sharing one fee accounting pass.
+    pub async fn batch(&self) -> Result<(), failure::Error> {
+        let main_account = &self.0.main_account;
+
+        // The whole batch is subsidized by the fee paid on the first transfer,
+        // so every other leg can be sent with a zero fee.
+        let batch_fee = self.0.transfer_fee(&main_account.zk_acc).await;
+        let base_nonce = main_account.zk_acc.nonce();
+
+        let (first, first_eth_sign) = Self::sign_transfer_with_nonce(
+            &main_account.zk_acc,
+            main_account.zk_acc.address,
+            1u32.into(),
+            batch_fee,
+            base_nonce,
+        );
+        let (second, second_eth_sign) = Self::sign_transfer_with_nonce(
+            &main_account.zk_acc,
+            main_account.zk_acc.address,
+            1u32.into(),
+            0u32.into(),
+            base_nonce + 1,
+        );
+
+        let batch_signature = Self::sign_batch(&main_account.zk_acc, &[&first, &second]);
+        let txs = vec![(first, first_eth_sign), (second, second_eth_sign)];
+
+        let reply = self
+            .0
+            .rpc_client
+            .submit_txs_batch(txs, batch_signature)
+            .await?;
+        match reply {
+            Output::Success(v) => {
+                log::debug!("Batch of transactions accepted: {:?}", v);
+            }
+            Output::Failure(v) => {
+                panic!("Got a failure response for a valid batch: {:?}", v);
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Submits a batch whose transactions do not form a continuous nonce chain
+    /// and expects it to be rejected as a whole with `RpcErrorCodes::BatchInvalid`.
+    pub async fn batch_incorrect_order(&self) -> Result<(), failure::Error> {
+        let main_account = &self.0.main_account;
+
+        let batch_fee = self.0.transfer_fee(&main_account.zk_acc).await;
+        let base_nonce = main_account.zk_acc.nonce();
+
+        let (first, first_eth_sign) = Self::sign_transfer_with_nonce(
+            &main_account.zk_acc,
+            main_account.zk_acc.address,
+            1u32.into(),
+            batch_fee,
+            base_nonce,
+        );
+        // Sign the second transfer with the very same nonce as the first one
+        // (instead of `base_nonce + 1`), so the batch as a whole does not form
+        // a continuous nonce chain.
+        let (second, second_eth_sign) = Self::sign_transfer_with_nonce(
+            &main_account.zk_acc,
+            main_account.zk_acc.address,
+            1u32.into(),
+            0u32.into(),
+            base_nonce,
+        );
+
+        let batch_signature = Self::sign_batch(&main_account.zk_acc, &[&first, &second]);
+        let txs = vec![(first, first_eth_sign), (second, second_eth_sign)];
+
+        let reply = self
+            .0
+            .rpc_client
+            .submit_txs_batch(txs, batch_signature)
+            .await?;
+        match reply {
+            Output::Success(v) => {
+                panic!(
+                    "Got successful response for a batch with broken nonce order: {:?}",
+                    v
+                );
+            }
+            Output::Failure(v) => {
+                self.check_rpc_code(v, RpcErrorCodes::BatchInvalid.into());
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Signs the batch as a whole: one ETH signature over the concatenation
+    /// of every transaction's hash, binding the batch together so that no
+    /// subset of it can be replayed independently of the rest.
+    fn sign_batch(signer: &ZksyncAccount, txs: &[&FranklinTx]) -> PackedEthSignature {
+        let mut batch_message = Vec::new();
+        for tx in txs {
+            batch_message.extend_from_slice(&tx.hash());
+        }
+
+        PackedEthSignature::sign(&signer.eth_private_key, &batch_message)
+            .expect("Signing the batch unexpectedly failed")
+    }
+
+    /// Runs `tx_simulate` over a tx that would be rejected by `submit_tx` and
+    /// expects the same `RpcErrorCodes::FeeTooLow` verdict, without the tx
+    /// actually being enqueued.
+    pub async fn simulate_low_fee(&self) -> Result<(), failure::Error> {
+        let main_account = &self.0.main_account;
+
+        // Set fee to 0.
+        let transfer_fee = 0u32;
+
+        let (transfer, eth_sign) = self.0.sign_transfer(
+            &main_account.zk_acc,
+            &main_account.zk_acc,
+            1u32,
+            transfer_fee,
+        );
+
+        let reply = self.0.rpc_client.tx_simulate(transfer, eth_sign).await?;
+        match reply {
+            Output::Success(v) => {
+                panic!(
+                    "Got successful simulation result for tx with too low fee: {:?}",
+                    v
+                );
+            }
+            Output::Failure(v) => {
+                self.check_rpc_code(v, RpcErrorCodes::FeeTooLow.into());
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Runs `tx_simulate` over a tx that `submit_tx` would accept and makes
+    /// sure the simulation reports success without mutating the committed
+    /// nonce, i.e. the very same tx can still be submitted for real
+    /// afterwards. A `tx_simulate` that diverged from `submit_tx`'s
+    /// validation (the two are supposed to share one `validate_tx` path) or
+    /// that accidentally enqueued the tx would show up here as either a
+    /// mismatched verdict or a bumped nonce before the real submission.
+    pub async fn simulate_valid_transfer(&self) -> Result<(), failure::Error> {
+        let main_account = &self.0.main_account;
+
+        let transfer_fee = self.0.transfer_fee(&main_account.zk_acc).await;
+
+        let (transfer, eth_sign) = self.0.sign_transfer(
+            &main_account.zk_acc,
+            &main_account.zk_acc,
+            1u32,
+            transfer_fee,
+        );
+
+        let nonce_before = self
+            .0
+            .rpc_client
+            .account_state(main_account.zk_acc.address)
+            .await?
+            .committed
+            .nonce;
+
+        let reply = self
+            .0
+            .rpc_client
+            .tx_simulate(transfer.clone(), eth_sign.clone())
+            .await?;
+        if let Output::Failure(v) = reply {
+            panic!(
+                "Got a failure simulation result for a valid transfer: {:?}",
+                v
+            );
+        }
+
+        let nonce_after = self
+            .0
+            .rpc_client
+            .account_state(main_account.zk_acc.address)
+            .await?
+            .committed
+            .nonce;
+        assert_eq!(
+            nonce_before, nonce_after,
+            "tx_simulate must not mutate mempool/nonce state"
+        );
+
+        // The simulation must not have touched the mempool/nonce state, so the
+        // very same signed tx can still be submitted for real.
+        let reply = self.0.rpc_client.send_tx_raw(transfer, eth_sign).await?;
+        if let Output::Failure(v) = reply {
+            panic!(
+                "Valid transfer rejected by submit_tx after a successful simulation: {:?}",
+                v
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Submits a `Transfer` with `account_id` left unset and expects the
+    /// server to resolve it from committed state using `from`, the same way
+    /// it would if the client had pre-resolved it itself.
+    pub async fn account_id_inferred_from_state(&self) -> Result<(), failure::Error> {
+        let main_account = &self.0.main_account;
+
+        let transfer_fee = self.0.transfer_fee(&main_account.zk_acc).await;
+
+        let (transfer, eth_sign) = Self::sign_transfer_without_account_id(
+            &main_account.zk_acc,
+            main_account.zk_acc.address,
+            1_u32.into(),
+            transfer_fee,
+        );
+
+        let reply = self.0.rpc_client.send_tx_raw(transfer, eth_sign).await?;
+        if let Output::Failure(v) = reply {
+            panic!(
+                "Got a failure response for a tx with an inferable account ID: {:?}",
+                v
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Submits a `Transfer` without `account_id` from an address that was
+    /// never registered in the tree and expects a distinct
+    /// `RpcErrorCodes::AccountNotFound`, rather than the generic `IncorrectTx`.
+    pub async fn account_not_found(&self) -> Result<(), failure::Error> {
+        let random_account = ZksyncAccount::rand();
+
+        let transfer_fee = self.0.transfer_fee(&random_account).await;
+
+        let (transfer, eth_sign) = Self::sign_transfer_without_account_id(
+            &random_account,
+            random_account.address,
+            10_u32.into(),
+            transfer_fee,
+        );
+
+        let reply = self.0.rpc_client.send_tx_raw(transfer, eth_sign).await?;
+        match reply {
+            Output::Success(v) => {
+                panic!(
+                    "Got successful response for a tx from an unregistered account: {:?}",
+                    v
+                );
+            }
+            Output::Failure(v) => {
+                self.check_rpc_code(v, RpcErrorCodes::AccountNotFound.into());
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Builds and submits a `Transfer` jointly authorized by several parties
+    /// through an N-of-N aggregated MuSig signature, and expects the circuit
+    /// to accept it transparently, as if it were a single-signer transfer.
+    pub async fn multisig_transfer(&self) -> Result<(), failure::Error> {
+        let main_account = &self.0.main_account;
+        let co_signer = ZksyncAccount::rand();
+        let signers = [&main_account.zk_acc, &co_signer];
+
+        // The circuit checks the signature against the account's registered
+        // pubkey hash, so the aggregate key must be registered before a
+        // jointly-signed transfer can be accepted.
+        self.register_aggregate_pubkey(&main_account.zk_acc, &signers)
+            .await?;
+
+        let transfer_fee = self.0.transfer_fee(&main_account.zk_acc).await;
+
+        // `aggregate_musig`'s internal `make_even` step takes a different
+        // branch depending on the parity of the summed per-signer nonce `R`,
+        // which is derived from each signer's key and the tx bytes (a new
+        // nonce each attempt); repeating the exchange a few times exercises
+        // both the even- and odd-`R` cases across runs, since the tester has
+        // no direct way to force either branch from the outside.
+        for attempt in 0..4 {
+            let (transfer, eth_sign) = Self::sign_transfer_multisig(
+                &signers,
+                main_account.zk_acc.address,
+                1_u32.into(),
+                transfer_fee.clone(),
+            );
+
+            let reply = self.0.rpc_client.send_tx_raw(transfer, eth_sign).await?;
+            if let Output::Failure(v) = reply {
+                panic!(
+                    "Got a failure response for a jointly authorized transfer (attempt {}): {:?}",
+                    attempt, v
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Registers the N-of-N aggregate of `signers`' public keys as the tree
+    /// pubkey hash for `account`, via a `ChangePubKey` tx signed by the
+    /// account's own key. Required before the circuit will accept a transfer
+    /// authorized by the signers' aggregated MuSig signature.
+    async fn register_aggregate_pubkey(
+        &self,
+        account: &ZksyncAccount,
+        signers: &[&ZksyncAccount],
+    ) -> Result<(), failure::Error> {
+        let public_keys: Vec<_> = signers.iter().map(|acc| &acc.public_key).collect();
+        let aggregate_pubkey_hash = TxSignature::aggregate_pubkey_hash(&public_keys);
+
+        let mut change_pubkey = ChangePubKey::new(
+            account.get_account_id().expect("Account ID must be set"),
+            account.address,
+            aggregate_pubkey_hash,
+            account.nonce(),
+            None,
+        );
+        change_pubkey.signature =
+            TxSignature::sign_musig(&account.private_key, &change_pubkey.get_bytes());
+
+        let reply = self
+            .0
+            .rpc_client
+            .send_tx_raw(FranklinTx::ChangePubKey(Box::new(change_pubkey)), None)
+            .await?;
+        if let Output::Failure(v) = reply {
+            panic!("Failed to register the aggregate pubkey hash: {:?}", v);
+        }
+
+        Ok(())
+    }
+
+    /// Creates a transfer jointly signed by `signers` via N-of-N aggregated
+    /// MuSig: every signer contributes to the aggregate key and nonce, and
+    /// the circuit verifies the result as a single Schnorr signature.
+    fn sign_transfer_multisig(
+        signers: &[&ZksyncAccount],
         to: Address,
         amount: BigUint,
         fee: BigUint,
     ) -> (FranklinTx, Option<PackedEthSignature>) {
         let token: TokenId = 0; // ETH token
-        let account_id = from.get_account_id().expect("Account ID must be set");
+        let sender = signers[0];
+        let account_id = sender.get_account_id().expect("Account ID must be set");
         let mut tx = Transfer::new(
             account_id,
+            sender.address,
+            to,
+            token,
+            amount,
+            fee,
+            sender.nonce(),
+            None,
+        );
+
+        let private_keys: Vec<_> = signers.iter().map(|acc| &acc.private_key).collect();
+        tx.signature = TxSignature::aggregate_musig(&private_keys, &tx.get_bytes());
+
+        let eth_signature = PackedEthSignature::sign(
+            &sender.eth_private_key,
+            tx.get_ethereum_sign_message("ETH").as_bytes(),
+        )
+        .expect("Signing the transfer unexpectedly failed");
+
+        (FranklinTx::Transfer(Box::new(tx)), Some(eth_signature))
+    }
+
+    /// Creates a signed transfer for a client that doesn't pre-resolve its
+    /// own `account_id`, mirroring `sign_transfer` for clients that don't
+    /// want to query their account ID before submitting their first
+    /// transaction.
+    ///
+    /// `Transfer::new` still takes a plain `AccountId`, not an
+    /// `Option<AccountId>` — `account_id` is a mempool/circuit routing field
+    /// resolved from the sender's address, and it is not part of
+    /// `get_bytes()`'s signed payload. So the placeholder passed here has no
+    /// bearing on the signature: the server fills in the real, resolved ID
+    /// before storing the tx, and re-derives the exact same signed bytes
+    /// regardless of which `account_id` ends up in the struct.
+    fn sign_transfer_without_account_id(
+        from: &ZksyncAccount,
+        to: Address,
+        amount: BigUint,
+        fee: BigUint,
+    ) -> (FranklinTx, Option<PackedEthSignature>) {
+        let token: TokenId = 0; // ETH token
+        let unresolved_account_id = 0;
+        let mut tx = Transfer::new(
+            unresolved_account_id,
             from.address,
             to,
             token,
@@ -248,4 +709,38 @@ impl<'a> SubmitTxTester<'a> {
 
         (FranklinTx::Transfer(Box::new(tx)), Some(eth_signature))
     }
+
+    /// Creates signed transfer without any checks for correctness.
+    fn sign_transfer(
+        from: &ZksyncAccount,
+        to: Address,
+        amount: BigUint,
+        fee: BigUint,
+    ) -> (FranklinTx, Option<PackedEthSignature>) {
+        Self::sign_transfer_with_nonce(from, to, amount, fee, from.nonce())
+    }
+
+    /// Like `sign_transfer`, but takes an explicit `nonce` instead of
+    /// advancing `from`'s own counter, so callers building a batch can
+    /// control whether the legs form a continuous chain.
+    fn sign_transfer_with_nonce(
+        from: &ZksyncAccount,
+        to: Address,
+        amount: BigUint,
+        fee: BigUint,
+        nonce: Nonce,
+    ) -> (FranklinTx, Option<PackedEthSignature>) {
+        let token: TokenId = 0; // ETH token
+        let account_id = from.get_account_id().expect("Account ID must be set");
+        let mut tx = Transfer::new(account_id, from.address, to, token, amount, fee, nonce, None);
+        tx.signature = TxSignature::sign_musig(&from.private_key, &tx.get_bytes());
+
+        let eth_signature = PackedEthSignature::sign(
+            &from.eth_private_key,
+            tx.get_ethereum_sign_message("ETH").as_bytes(),
+        )
+        .expect("Signing the transfer unexpectedly failed");
+
+        (FranklinTx::Transfer(Box::new(tx)), Some(eth_signature))
+    }
 }
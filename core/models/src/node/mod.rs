@@ -0,0 +1,17 @@
+//! Core domain types shared by the RPC server, RPC client, and test harnesses.
+
+pub mod tx;
+
+pub use tx::FranklinTx;
+
+/// Sequential index of a registered account in the state tree.
+pub type AccountId = u32;
+
+/// Identifier of a token registered with the network (0 is ETH).
+pub type TokenId = u16;
+
+/// Per-account transaction counter, incremented with every processed tx.
+pub type Nonce = u32;
+
+/// An Ethereum L1 address.
+pub type Address = [u8; 20];
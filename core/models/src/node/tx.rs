@@ -0,0 +1,414 @@
+//! Transaction types and signing primitives.
+//!
+//! The production signer verifies zkSync-native signatures over the Jubjub
+//! curve via `franklin_crypto`, and Ethereum signatures via `secp256k1`
+//! ECDSA recovery; neither of those crates is vendored in this tree. The
+//! group below is the subgroup of `Z_p^*` generated by 2 (its order is
+//! composite) standing in for Jubjub,
+//! and `PackedEthSignature` stands in for the real ECDSA path — both keep
+//! the same external shape (a signature verifies against one public key,
+//! recovery returns the signing address) so the rest of the codebase can be
+//! written against them normally.
+
+use num::BigUint;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use super::{AccountId, Address, Nonce, TokenId};
+
+fn group_modulus() -> BigUint {
+    // secp256k1's field prime, reused purely as a convenient large prime.
+    BigUint::parse_bytes(
+        b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F",
+        16,
+    )
+    .expect("hardcoded modulus must parse")
+}
+
+fn generator() -> BigUint {
+    BigUint::from(2u32)
+}
+
+/// Order of `Z_p^*` as a whole, `group_modulus() - 1`; the order of the
+/// subgroup `generator()` generates divides it, so signature scalars
+/// (nonces, shares) reduce modulo this, not the modulus.
+fn group_order() -> BigUint {
+    group_modulus() - BigUint::from(1u32)
+}
+
+/// A zkSync account signing key.
+#[derive(Clone)]
+pub struct PrivateKey(pub BigUint);
+
+/// The public key corresponding to a `PrivateKey`, `G^x mod P`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PublicKey(pub BigUint);
+
+impl PrivateKey {
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(generator().modpow(&self.0, &group_modulus()))
+    }
+}
+
+impl PublicKey {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes_be()
+    }
+}
+
+/// A zkSync-native Schnorr signature over a transaction's canonical bytes.
+#[derive(Clone)]
+pub struct TxSignature {
+    pub r: BigUint,
+    pub s: BigUint,
+}
+
+impl TxSignature {
+    /// Single-party signature over `message`.
+    pub fn sign_musig(private_key: &PrivateKey, message: &[u8]) -> Self {
+        Self::aggregate_musig(&[private_key], message)
+    }
+
+    /// An N-of-N MuSig aggregated signature jointly authorizing `message` on
+    /// behalf of every key in `private_keys`. Follows the standard two-round
+    /// MuSig shape collapsed into one pass (every signer's nonce/share is
+    /// derived deterministically from its key and `message`, so there's no
+    /// need for an actual network
+    /// round-trip here): key-aggregation coefficients `a_i = H(L, X_i)`,
+    /// aggregate key `X = Σ a_i·X_i`, aggregate nonce `R = Σ R_i`, challenge
+    /// `e = H(R, X, m)`, and per-signer shares `s_i = r_i + e·a_i·x_i`.
+    pub fn aggregate_musig(private_keys: &[&PrivateKey], message: &[u8]) -> Self {
+        let modulus = group_modulus();
+        let order = group_order();
+
+        let public_keys: Vec<PublicKey> = private_keys.iter().map(|k| k.public_key()).collect();
+        let coefficients = key_aggregation_coefficients(&public_keys);
+        let aggregate_key = aggregate_public_key(&public_keys, &coefficients);
+
+        let nonce_scalars: Vec<BigUint> = private_keys
+            .iter()
+            .map(|k| hash_to_scalar(&[b"musig-nonce", &k.0.to_bytes_be(), message]))
+            .collect();
+        let r = nonce_scalars
+            .iter()
+            .fold(BigUint::from(0u32), |acc, r_i| (acc + r_i) % &order);
+        let aggregate_r = generator().modpow(&r, &modulus);
+
+        let e = hash_to_scalar(&[
+            &aggregate_r.to_bytes_be(),
+            &aggregate_key.0.to_bytes_be(),
+            message,
+        ]);
+
+        let s_shares: Vec<BigUint> = private_keys
+            .iter()
+            .zip(nonce_scalars.iter())
+            .zip(coefficients.iter())
+            .map(|((key, r_i), a_i)| (r_i + &e * a_i * &key.0) % &order)
+            .collect();
+        let s = s_shares
+            .iter()
+            .fold(BigUint::from(0u32), |acc, s_i| (acc + s_i) % &order);
+
+        make_even(aggregate_r, s, modulus, order)
+    }
+
+    /// The pubkey hash the circuit checks a jointly-signed transfer's
+    /// signature against: the hash of the N-of-N aggregate of `public_keys`.
+    pub fn aggregate_pubkey_hash(public_keys: &[&PublicKey]) -> PubKeyHash {
+        let owned: Vec<PublicKey> = public_keys.iter().map(|k| (*k).clone()).collect();
+        let coefficients = key_aggregation_coefficients(&owned);
+        PubKeyHash::from_pubkey(&aggregate_public_key(&owned, &coefficients))
+    }
+}
+
+/// `a_i = H(L, X_i)` for every key in `keys`, where `L` is the bytes of all
+/// keys concatenated in the order given (so the result depends on that
+/// order) — binds each coefficient to the full signer set so a
+/// rogue-key attack can't cancel out another signer's contribution.
+fn key_aggregation_coefficients(keys: &[PublicKey]) -> Vec<BigUint> {
+    let l: Vec<u8> = keys.iter().flat_map(|k| k.to_bytes()).collect();
+    keys.iter()
+        .map(|k| hash_to_scalar(&[b"musig-coefficient", &l, &k.to_bytes()]))
+        .collect()
+}
+
+fn aggregate_public_key(keys: &[PublicKey], coefficients: &[BigUint]) -> PublicKey {
+    let modulus = group_modulus();
+    let product = keys.iter().zip(coefficients.iter()).fold(
+        BigUint::from(1u32),
+        |acc, (key, a_i)| (acc * key.0.modpow(a_i, &modulus)) % &modulus,
+    );
+    PublicKey(product)
+}
+
+/// MuSig (like plain Schnorr here) only commits to `R`'s x-coordinate, which
+/// doesn't capture its sign/parity; canonicalize by negating `R` (and every
+/// share that contributed to `s`) whenever `R` would otherwise be "odd", so
+/// verification doesn't need to try both parities.
+fn make_even(r: BigUint, s: BigUint, modulus: BigUint, order: BigUint) -> TxSignature {
+    if &r % 2u32 == BigUint::from(0u32) {
+        TxSignature { r, s }
+    } else {
+        let r = (&modulus - &r) % &modulus;
+        let s = (&order - &s) % &order;
+        TxSignature { r, s }
+    }
+}
+
+/// Hashes `parts` into a scalar. Stands in for the Pedersen/Blake2
+/// hash-to-scalar the real signer uses.
+pub(crate) fn hash_to_scalar(parts: &[&[u8]]) -> BigUint {
+    let mut seed = {
+        let mut hasher = DefaultHasher::new();
+        for part in parts {
+            part.hash(&mut hasher);
+        }
+        hasher.finish()
+    };
+
+    // `DefaultHasher` only yields 64 bits; chain it a few rounds so the
+    // scalar spans 256 bits before the reduction (it still carries only the
+    // 64 bits of entropy of the first round).
+    let mut bytes = Vec::with_capacity(32);
+    for _ in 0..4 {
+        bytes.extend_from_slice(&seed.to_le_bytes());
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        seed = hasher.finish();
+    }
+
+    BigUint::from_bytes_le(&bytes) % group_modulus()
+}
+
+/// An Ethereum (secp256k1 ECDSA) signature binding a tx to its L1 account.
+#[derive(Clone)]
+pub struct PackedEthSignature(pub [u8; 65]);
+
+impl PackedEthSignature {
+    /// Signs `message`. The stand-in signer's address is the first 20 bytes
+    /// of `private_key`; it is embedded in the signature together with a
+    /// digest binding it to `message`.
+    pub fn sign(private_key: &[u8; 32], message: &[u8]) -> Result<Self, failure::Error> {
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&private_key[..20]);
+
+        let mut bytes = [0u8; 65];
+        bytes[..20].copy_from_slice(&address);
+        bytes[20..28].copy_from_slice(&signed_digest(&address, message));
+        Ok(PackedEthSignature(bytes))
+    }
+
+    pub fn deserialize_packed(bytes: &[u8]) -> Result<Self, failure::Error> {
+        if bytes.len() != 65 {
+            failure::bail!(
+                "PackedEthSignature must be exactly 65 bytes, got {}",
+                bytes.len()
+            );
+        }
+        let mut out = [0u8; 65];
+        out.copy_from_slice(bytes);
+        Ok(PackedEthSignature(out))
+    }
+
+    /// Recovers the address that produced this signature over `message`; a
+    /// signature not made over `message` recovers an unrelated address.
+    pub fn signature_recover_signer(&self, message: &[u8]) -> Result<Address, failure::Error> {
+        let mut signer = [0u8; 20];
+        signer.copy_from_slice(&self.0[..20]);
+        if self.0[20..28] == signed_digest(&signer, message) {
+            return Ok(signer);
+        }
+
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        message.hash(&mut hasher);
+        let digest = hasher.finish().to_be_bytes();
+
+        let mut address = [0u8; 20];
+        address[..8].copy_from_slice(&digest);
+        address[8..16].copy_from_slice(&digest);
+        Ok(address)
+    }
+}
+
+fn signed_digest(address: &Address, message: &[u8]) -> [u8; 8] {
+    let mut hasher = DefaultHasher::new();
+    address.hash(&mut hasher);
+    message.hash(&mut hasher);
+    hasher.finish().to_be_bytes()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The hash of a public key the tree stores per-account; a transfer's
+/// signature is checked against the hash registered for its sender, not the
+/// raw public key itself.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PubKeyHash(pub [u8; 20]);
+
+impl PubKeyHash {
+    pub fn from_pubkey(public_key: &PublicKey) -> Self {
+        let mut hasher = DefaultHasher::new();
+        public_key.0.to_bytes_be().hash(&mut hasher);
+        let digest = hasher.finish().to_be_bytes();
+
+        let mut out = [0u8; 20];
+        out[..8].copy_from_slice(&digest);
+        out[8..16].copy_from_slice(&digest);
+        PubKeyHash(out)
+    }
+}
+
+/// A signed transfer of `amount` of `token` from one account to another,
+/// plus a `fee` paid to the operator.
+#[derive(Clone)]
+pub struct Transfer {
+    pub account_id: AccountId,
+    pub from: Address,
+    pub to: Address,
+    pub token: TokenId,
+    pub amount: BigUint,
+    pub fee: BigUint,
+    pub nonce: Nonce,
+    pub signature: TxSignature,
+}
+
+impl Transfer {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        account_id: AccountId,
+        from: Address,
+        to: Address,
+        token: TokenId,
+        amount: BigUint,
+        fee: BigUint,
+        nonce: Nonce,
+        _time_range: Option<()>,
+    ) -> Self {
+        Self {
+            account_id,
+            from,
+            to,
+            token,
+            amount,
+            fee,
+            nonce,
+            // Replaced by the caller once the canonical bytes are known.
+            signature: TxSignature {
+                r: BigUint::from(0u32),
+                s: BigUint::from(0u32),
+            },
+        }
+    }
+
+    /// Canonical byte encoding signed by `TxSignature::sign_musig`.
+    /// `account_id` is deliberately excluded: it is a mempool/circuit
+    /// routing field resolved from `from`'s address, not part of what the
+    /// signer authorizes.
+    pub fn get_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.from);
+        out.extend_from_slice(&self.to);
+        out.extend_from_slice(&self.token.to_be_bytes());
+        out.extend_from_slice(&self.amount.to_bytes_be());
+        out.extend_from_slice(&self.fee.to_bytes_be());
+        out.extend_from_slice(&self.nonce.to_be_bytes());
+        out
+    }
+
+    pub fn get_ethereum_sign_message(&self, token_symbol: &str) -> String {
+        format!(
+            "Transfer {} {} to 0x{}\nFee: {} {}\nNonce: {}",
+            self.amount,
+            token_symbol,
+            encode_hex(&self.to),
+            self.fee,
+            token_symbol,
+            self.nonce
+        )
+    }
+}
+
+/// Registers `new_pk_hash` as the pubkey hash the circuit checks `account`'s
+/// future signatures against — how an account (re-)binds the key(s)
+/// authorized to sign on its behalf, including an N-of-N MuSig aggregate.
+#[derive(Clone)]
+pub struct ChangePubKey {
+    pub account_id: AccountId,
+    pub account: Address,
+    pub new_pk_hash: PubKeyHash,
+    pub nonce: Nonce,
+    pub signature: TxSignature,
+}
+
+impl ChangePubKey {
+    pub fn new(
+        account_id: AccountId,
+        account: Address,
+        new_pk_hash: PubKeyHash,
+        nonce: Nonce,
+        _time_range: Option<()>,
+    ) -> Self {
+        Self {
+            account_id,
+            account,
+            new_pk_hash,
+            nonce,
+            signature: TxSignature {
+                r: BigUint::from(0u32),
+                s: BigUint::from(0u32),
+            },
+        }
+    }
+
+    pub fn get_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.account);
+        out.extend_from_slice(&self.new_pk_hash.0);
+        out.extend_from_slice(&self.nonce.to_be_bytes());
+        out
+    }
+}
+
+/// Any transaction the network accepts, keyed by its concrete type.
+#[derive(Clone)]
+pub enum FranklinTx {
+    Transfer(Box<Transfer>),
+    ChangePubKey(Box<ChangePubKey>),
+}
+
+impl FranklinTx {
+    /// A content hash of the tx, used to bind a batch together (see
+    /// `submit_txs_batch`'s batch signature).
+    pub fn hash(&self) -> Vec<u8> {
+        let mut hasher = DefaultHasher::new();
+        match self {
+            FranklinTx::Transfer(tx) => {
+                b"transfer".hash(&mut hasher);
+                tx.get_bytes().hash(&mut hasher);
+            }
+            FranklinTx::ChangePubKey(tx) => {
+                b"change_pubkey".hash(&mut hasher);
+                tx.get_bytes().hash(&mut hasher);
+            }
+        }
+        hasher.finish().to_be_bytes().to_vec()
+    }
+
+    pub fn account_id(&self) -> AccountId {
+        match self {
+            FranklinTx::Transfer(tx) => tx.account_id,
+            FranklinTx::ChangePubKey(tx) => tx.account_id,
+        }
+    }
+
+    pub fn nonce(&self) -> Nonce {
+        match self {
+            FranklinTx::Transfer(tx) => tx.nonce,
+            FranklinTx::ChangePubKey(tx) => tx.nonce,
+        }
+    }
+}
@@ -0,0 +1,3 @@
+//! JSON-RPC server exposed to wallets and the load-testing harness.
+
+pub mod rpc_server;
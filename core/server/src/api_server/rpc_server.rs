@@ -0,0 +1,358 @@
+//! `submit_tx` and friends: the JSON-RPC surface wallets use to get a
+//! transaction into the mempool.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use jsonrpc_core::{Error as RpcError, ErrorCode};
+use num::{BigUint, ToPrimitive};
+
+use models::node::tx::{FranklinTx, PackedEthSignature, PubKeyHash};
+use models::node::{AccountId, Address, Nonce};
+
+/// Rejection reasons `submit_tx` (and the methods alongside it) can return.
+/// Reported as `jsonrpc_core::Error::code` via `ErrorCode::ServerError` with a
+/// fixed code (101 to 106), so wallets can match on a stable, documented code
+/// rather than the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcErrorCodes {
+    MissingEthSignature,
+    IncorrectEthSignature,
+    FeeTooLow,
+    IncorrectTx,
+    /// A submitted batch was rejected as a whole: either the batch did not
+    /// form a continuous nonce chain, or the batch signature does not bind
+    /// its txs. A leg that fails validation is reported with its own code.
+    BatchInvalid,
+    /// `account_id` was omitted (or a placeholder) and `from`'s address has
+    /// no account in committed state to resolve it from.
+    AccountNotFound,
+}
+
+impl From<RpcErrorCodes> for ErrorCode {
+    fn from(code: RpcErrorCodes) -> ErrorCode {
+        let offset = match code {
+            RpcErrorCodes::MissingEthSignature => 101,
+            RpcErrorCodes::IncorrectEthSignature => 102,
+            RpcErrorCodes::FeeTooLow => 103,
+            RpcErrorCodes::IncorrectTx => 104,
+            RpcErrorCodes::BatchInvalid => 105,
+            RpcErrorCodes::AccountNotFound => 106,
+        };
+        ErrorCode::ServerError(offset)
+    }
+}
+
+/// The subset of committed state the RPC methods read: registered accounts'
+/// ids and nonces, plus the network's minimum fee. Pubkey hashes are kept
+/// here too; no check in this module reads them.
+#[derive(Default)]
+pub struct NetworkState {
+    account_ids_by_address: HashMap<Address, AccountId>,
+    nonces_by_account: HashMap<AccountId, Nonce>,
+    pubkey_hashes_by_account: HashMap<AccountId, PubKeyHash>,
+    min_fee: BigUint,
+}
+
+impl NetworkState {
+    pub fn account_id(&self, address: &Address) -> Option<AccountId> {
+        self.account_ids_by_address.get(address).copied()
+    }
+
+    pub fn nonce(&self, account_id: AccountId) -> Nonce {
+        self.nonces_by_account.get(&account_id).copied().unwrap_or(0)
+    }
+
+    pub fn pubkey_hash(&self, account_id: AccountId) -> Option<PubKeyHash> {
+        self.pubkey_hashes_by_account.get(&account_id).copied()
+    }
+}
+
+pub struct RpcApp {
+    state: Arc<Mutex<NetworkState>>,
+}
+
+impl RpcApp {
+    pub fn new(state: Arc<Mutex<NetworkState>>) -> Self {
+        Self { state }
+    }
+
+    /// Accepts `tx` into the mempool if it passes `validate_tx`. A
+    /// placeholder `account_id` of 0 is replaced by the id resolved from the
+    /// sender's address before the tx is enqueued.
+    pub fn submit_tx(
+        &self,
+        tx: FranklinTx,
+        eth_signature: Option<PackedEthSignature>,
+    ) -> Result<(), RpcError> {
+        let state = self.state.lock().unwrap();
+        validate_tx(&state, &tx, eth_signature.as_ref())?;
+
+        let mut tx = tx;
+        if let Some(account_id) = state.account_id(&resolved_sender(&state, &tx)?) {
+            match &mut tx {
+                FranklinTx::Transfer(t) => t.account_id = account_id,
+                FranklinTx::ChangePubKey(t) => t.account_id = account_id,
+            }
+        }
+        drop(state);
+
+        self.enqueue(tx);
+        Ok(())
+    }
+
+    fn enqueue(&self, _tx: FranklinTx) {
+        // The actual mempool/dispatch to the state keeper lives outside the
+        // scope of this RPC-facing module.
+    }
+
+    /// Validates `txs` as an atomic unit and, only if every leg passes,
+    /// enqueues all of them in order. The fee requirement is checked once
+    /// over the whole batch (so a later, zero-fee leg is fine as long as an
+    /// earlier one covers it), and `batch_signature` must be a valid ETH
+    /// signature over the concatenation of every tx's hash.
+    pub fn submit_txs_batch(
+        &self,
+        txs: Vec<(FranklinTx, Option<PackedEthSignature>)>,
+        batch_signature: PackedEthSignature,
+    ) -> Result<(), RpcError> {
+        let state = self.state.lock().unwrap();
+
+        for (tx, eth_signature) in &txs {
+            validate_tx_signatures(&state, tx, eth_signature.as_ref())?;
+        }
+
+        if !batch_nonces_are_continuous(&state, &txs) {
+            return Err(batch_invalid_error("batch nonces are not a continuous chain"));
+        }
+
+        let mut batch_message = Vec::new();
+        for (tx, _) in &txs {
+            batch_message.extend_from_slice(&tx.hash());
+        }
+        let signers: Vec<Address> = txs
+            .iter()
+            .map(|(tx, _)| resolved_sender(&state, tx))
+            .collect::<Result<_, _>>()?;
+        if !batch_signature_binds(&signers, &batch_message, &batch_signature) {
+            return Err(batch_invalid_error("batch signature does not bind the submitted txs"));
+        }
+
+        // The whole batch shares one fee accounting pass: the sum of every
+        // leg's fee must clear the minimum for the batch as a whole, rather
+        // than each leg clearing it individually.
+        let total_fee: BigUint = txs
+            .iter()
+            .map(|(tx, _)| tx_fee(tx))
+            .fold(BigUint::from(0u32), |acc, fee| acc + fee);
+        if total_fee < state.min_fee {
+            return Err(fee_too_low_error(&state.min_fee));
+        }
+
+        drop(state);
+
+        for (tx, _) in txs {
+            self.enqueue(tx);
+        }
+        Ok(())
+    }
+}
+
+fn resolved_sender(state: &NetworkState, tx: &FranklinTx) -> Result<Address, RpcError> {
+    let address = match tx {
+        FranklinTx::Transfer(t) => t.from,
+        FranklinTx::ChangePubKey(t) => t.account,
+    };
+    if state.account_id(&address).is_some() {
+        Ok(address)
+    } else {
+        Err(account_not_found_error())
+    }
+}
+
+fn tx_fee(tx: &FranklinTx) -> BigUint {
+    match tx {
+        FranklinTx::Transfer(t) => t.fee.clone(),
+        FranklinTx::ChangePubKey(_) => BigUint::from(0u32),
+    }
+}
+
+fn batch_nonces_are_continuous(
+    state: &NetworkState,
+    txs: &[(FranklinTx, Option<PackedEthSignature>)],
+) -> bool {
+    let mut expected: Option<(AccountId, Nonce)> = None;
+    for (tx, _) in txs {
+        let account_id = tx.account_id();
+        let nonce = tx.nonce();
+        match expected {
+            Some((prev_account, prev_nonce)) if prev_account == account_id => {
+                if nonce != prev_nonce + 1 {
+                    return false;
+                }
+            }
+            _ => {
+                if nonce != state.nonce(account_id) {
+                    return false;
+                }
+            }
+        }
+        expected = Some((account_id, nonce));
+    }
+    true
+}
+
+fn batch_signature_binds(
+    signers: &[Address],
+    batch_message: &[u8],
+    batch_signature: &PackedEthSignature,
+) -> bool {
+    signers.iter().any(|signer| {
+        batch_signature
+            .signature_recover_signer(batch_message)
+            .map(|recovered| &recovered == signer)
+            .unwrap_or(false)
+    })
+}
+
+/// Runs the exact same checks `submit_tx` does, but never enqueues `tx` or
+/// mutates mempool/nonce state — used to pre-flight a tx before committing
+/// to it.
+pub fn tx_simulate(
+    state: &NetworkState,
+    tx: &FranklinTx,
+    eth_signature: Option<&PackedEthSignature>,
+) -> Result<(), RpcError> {
+    validate_tx(state, tx, eth_signature)
+}
+
+/// The single validation pipeline `submit_tx` and `tx_simulate` both route
+/// through, so the two paths can never diverge. `submit_txs_batch` runs
+/// `validate_tx_signatures` on each leg and checks the fee over the batch.
+fn validate_tx(
+    state: &NetworkState,
+    tx: &FranklinTx,
+    eth_signature: Option<&PackedEthSignature>,
+) -> Result<(), RpcError> {
+    validate_tx_signatures(state, tx, eth_signature)?;
+
+    if let FranklinTx::Transfer(transfer) = tx {
+        if transfer.fee < state.min_fee {
+            return Err(fee_too_low_error(&state.min_fee));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_tx_signatures(
+    state: &NetworkState,
+    tx: &FranklinTx,
+    eth_signature: Option<&PackedEthSignature>,
+) -> Result<(), RpcError> {
+    let (address, account_id) = match tx {
+        FranklinTx::Transfer(transfer) => {
+            let eth_signature = eth_signature.ok_or_else(missing_eth_signature_error)?;
+            let message = transfer.get_ethereum_sign_message("ETH");
+
+            let recovered = eth_signature
+                .signature_recover_signer(message.as_bytes())
+                .map_err(|_| incorrect_eth_signature_error(&[0u8; 20], &transfer.from))?;
+            if recovered != transfer.from {
+                return Err(incorrect_eth_signature_error(&recovered, &transfer.from));
+            }
+
+            (transfer.from, transfer.account_id)
+        }
+        // A ChangePubKey carries no ETH signature, so there is nothing to
+        // recover here. The zkSync-native (MuSig) signature of a tx, of
+        // either kind, is not verified by this server.
+        FranklinTx::ChangePubKey(change_pubkey) => {
+            (change_pubkey.account, change_pubkey.account_id)
+        }
+    };
+
+    // Resolve (and cross-check) `account_id` from committed state. Wire
+    // callers that don't yet know their id pass a placeholder (0) here; it
+    // has no bearing on the signed bytes (see `Transfer::get_bytes`), so the
+    // resolved id below is always authoritative and lets the server infer a
+    // caller's account id instead of requiring them to look it up first.
+    let resolved_account_id = match state.account_id(&address) {
+        Some(id) => id,
+        None if account_id == 0 => return Err(account_not_found_error()),
+        None => return Err(incorrect_tx_error(None)),
+    };
+    if account_id != 0 && account_id != resolved_account_id {
+        return Err(incorrect_tx_error(Some(resolved_account_id)));
+    }
+
+    Ok(())
+}
+
+fn missing_eth_signature_error() -> RpcError {
+    RpcError {
+        code: RpcErrorCodes::MissingEthSignature.into(),
+        message: "Eth signature is required".into(),
+        data: None,
+    }
+}
+
+fn incorrect_eth_signature_error(recovered: &Address, expected: &Address) -> RpcError {
+    RpcError {
+        code: RpcErrorCodes::IncorrectEthSignature.into(),
+        message: "Eth signature is incorrect".into(),
+        data: Some(serde_json::json!({
+            "signer": {
+                "recovered": encode_address(recovered),
+                "expected": encode_address(expected),
+            }
+        })),
+    }
+}
+
+fn fee_too_low_error(min_fee: &BigUint) -> RpcError {
+    RpcError {
+        code: RpcErrorCodes::FeeTooLow.into(),
+        message: "Transaction fee is too low".into(),
+        data: Some(serde_json::json!({
+            "min_fee": min_fee.to_u64().unwrap_or(u64::MAX),
+        })),
+    }
+}
+
+/// `expected_account_id` is `None` (sent as `null`) when the sender's address
+/// has no account in committed state.
+fn incorrect_tx_error(expected_account_id: Option<AccountId>) -> RpcError {
+    RpcError {
+        code: RpcErrorCodes::IncorrectTx.into(),
+        message: "Transaction is incorrect".into(),
+        data: Some(serde_json::json!({
+            "expected_account_id": expected_account_id,
+        })),
+    }
+}
+
+fn account_not_found_error() -> RpcError {
+    RpcError {
+        code: RpcErrorCodes::AccountNotFound.into(),
+        message: "Account was not found in committed state".into(),
+        data: None,
+    }
+}
+
+fn batch_invalid_error(reason: &str) -> RpcError {
+    RpcError {
+        code: RpcErrorCodes::BatchInvalid.into(),
+        message: format!("Batch is invalid: {}", reason),
+        data: None,
+    }
+}
+
+fn encode_address(address: &Address) -> String {
+    let mut out = String::with_capacity(42);
+    out.push_str("0x");
+    for byte in address {
+        out.push_str(&format!("{:02x}", byte));
+    }
+    out
+}
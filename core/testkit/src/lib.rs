@@ -0,0 +1,2 @@
+pub mod rpc_client;
+pub mod zksync_account;
@@ -0,0 +1,93 @@
+//! Thin client over the JSON-RPC server used by the load-testing harness.
+//!
+//! The production client talks to the server over HTTP; this tree doesn't
+//! vendor an HTTP stack, so this talks to `server::api_server::rpc_server`
+//! in-process instead. The request/response shape (and therefore what the
+//! tests in `core/loadtest` observe) is the same either way.
+
+use std::sync::{Arc, Mutex};
+
+use jsonrpc_core::types::{Failure, Output, Success, Version};
+use jsonrpc_core::Id;
+
+use models::node::tx::PackedEthSignature;
+use models::node::{Address, FranklinTx, Nonce};
+use server::api_server::rpc_server::{self, NetworkState, RpcApp};
+
+pub struct CommittedAccountState {
+    pub nonce: Nonce,
+}
+
+pub struct AccountStateInfo {
+    pub committed: CommittedAccountState,
+}
+
+pub struct RpcClient {
+    app: RpcApp,
+    state: Arc<Mutex<NetworkState>>,
+}
+
+impl RpcClient {
+    pub fn new(state: Arc<Mutex<NetworkState>>) -> Self {
+        Self {
+            app: RpcApp::new(state.clone()),
+            state,
+        }
+    }
+
+    pub async fn send_tx_raw(
+        &self,
+        tx: FranklinTx,
+        eth_signature: Option<PackedEthSignature>,
+    ) -> Result<Output, failure::Error> {
+        Ok(to_output(self.app.submit_tx(tx, eth_signature)))
+    }
+
+    /// Submits `txs` as a single atomic batch, bound by `batch_signature`
+    /// (an ETH signature over the concatenation of every tx's hash).
+    pub async fn submit_txs_batch(
+        &self,
+        txs: Vec<(FranklinTx, Option<PackedEthSignature>)>,
+        batch_signature: PackedEthSignature,
+    ) -> Result<Output, failure::Error> {
+        Ok(to_output(self.app.submit_txs_batch(txs, batch_signature)))
+    }
+
+    /// Dry-runs `tx` through the same validation `send_tx_raw` uses, without
+    /// enqueueing it or mutating mempool/nonce state.
+    pub async fn tx_simulate(
+        &self,
+        tx: FranklinTx,
+        eth_signature: Option<PackedEthSignature>,
+    ) -> Result<Output, failure::Error> {
+        let state = self.state.lock().unwrap();
+        let result = rpc_server::tx_simulate(&state, &tx, eth_signature.as_ref());
+        Ok(to_output(result))
+    }
+
+    pub async fn account_state(&self, address: Address) -> Result<AccountStateInfo, failure::Error> {
+        let state = self.state.lock().unwrap();
+        let nonce = state
+            .account_id(&address)
+            .map(|id| state.nonce(id))
+            .unwrap_or(0);
+        Ok(AccountStateInfo {
+            committed: CommittedAccountState { nonce },
+        })
+    }
+}
+
+fn to_output(result: Result<(), jsonrpc_core::Error>) -> Output {
+    match result {
+        Ok(()) => Output::Success(Success {
+            jsonrpc: Some(Version::V2),
+            result: serde_json::Value::Bool(true),
+            id: Id::Num(0),
+        }),
+        Err(error) => Output::Failure(Failure {
+            jsonrpc: Some(Version::V2),
+            error,
+            id: Id::Num(0),
+        }),
+    }
+}
@@ -0,0 +1,78 @@
+//! A locally-held zkSync account: keys plus whatever identifiers the test
+//! harness has learned about it (account id, nonce), used to build and sign
+//! transactions without going through a wallet.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use models::node::tx::{PrivateKey, PublicKey};
+use models::node::{AccountId, Address, Nonce};
+
+pub struct ZksyncAccount {
+    pub address: Address,
+    pub private_key: PrivateKey,
+    pub public_key: PublicKey,
+    pub eth_private_key: [u8; 32],
+    account_id: Cell<Option<AccountId>>,
+    nonce: AtomicU32,
+}
+
+impl ZksyncAccount {
+    /// A fresh account with a random keypair, no known account id yet, and
+    /// nonce 0.
+    pub fn rand() -> Self {
+        let seed = rand_seed();
+        let private_key = PrivateKey(seed_to_scalar(seed));
+        let public_key = private_key.public_key();
+
+        Self {
+            address: seed_to_address(seed),
+            private_key,
+            public_key,
+            eth_private_key: seed_to_eth_key(seed),
+            account_id: Cell::new(None),
+            nonce: AtomicU32::new(0),
+        }
+    }
+
+    pub fn get_account_id(&self) -> Option<AccountId> {
+        self.account_id.get()
+    }
+
+    pub fn set_account_id(&self, account_id: Option<AccountId>) {
+        self.account_id.set(account_id);
+    }
+
+    /// Returns the current nonce and advances the local counter, mirroring
+    /// what the network will expect the next signed tx to carry.
+    pub fn nonce(&self) -> Nonce {
+        self.nonce.fetch_add(1, Ordering::SeqCst)
+    }
+}
+
+fn rand_seed() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    // A real build would use a CSPRNG; this only needs to be distinct across
+    // accounts within a single test run.
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    nanos ^ (std::ptr::addr_of!(nanos) as u64)
+}
+
+fn seed_to_scalar(seed: u64) -> num::BigUint {
+    num::BigUint::from(seed) + num::BigUint::from(1u32)
+}
+
+fn seed_to_address(seed: u64) -> Address {
+    let mut out = [0u8; 20];
+    out[..8].copy_from_slice(&seed.to_be_bytes());
+    out
+}
+
+fn seed_to_eth_key(seed: u64) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out[..8].copy_from_slice(&seed.to_be_bytes());
+    out
+}